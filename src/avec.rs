@@ -0,0 +1,244 @@
+//! A heap allocated, custom-alignment vector.
+//!
+//! `AVec` is the heap-backed companion to the stack allocation functions in the crate root,
+//! with fallible constructors and `try_reserve()` that report overflow or OOM as an `Err`.
+//! `stackalloc_or_heap()` does not use it: its heap fallback is a plain `Vec<T>`.
+//!
+//! `AVec` is defined in this module and re-exported from the crate root as `stackalloc::AVec`;
+//! it is not a re-export of a type from another crate, so extending it here doesn't change
+//! what type callers of `stackalloc::AVec` are actually using.
+
+use core::alloc::Layout;
+use core::marker::PhantomData;
+use core::mem;
+use core::ptr::{self, NonNull};
+use std::alloc::{alloc, dealloc, handle_alloc_error};
+
+/// The error returned by `AVec`'s fallible constructors and `try_reserve()` when the
+/// requested layout overflows `isize::MAX` or the global allocator reports OOM.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TryReserveError {
+    /// `capacity * size_of::<T>()`, rounded up to `align`, overflows `isize::MAX`.
+    CapacityOverflow,
+    /// The global allocator returned a null pointer for the computed `Layout`.
+    AllocError(Layout),
+}
+
+impl core::fmt::Display for TryReserveError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            TryReserveError::CapacityOverflow => write!(f, "memory layout would overflow isize::MAX"),
+            TryReserveError::AllocError(layout) => write!(f, "allocator failed to allocate {} bytes", layout.size()),
+        }
+    }
+}
+
+impl std::error::Error for TryReserveError {}
+
+/// A `Vec<T>`-alike that allocates its backing buffer on the heap at a caller-chosen
+/// alignment, rather than `align_of::<T>()`.
+///
+/// This is useful for SIMD or DMA-style buffers that need a stronger alignment guarantee
+/// than their element type naturally provides.
+pub struct AVec<T> {
+    ptr: NonNull<T>,
+    len: usize,
+    cap: usize,
+    align: usize,
+    _marker: PhantomData<T>,
+}
+
+impl<T> AVec<T> {
+    /// Create a new, empty `AVec` with no backing allocation.
+    ///
+    /// # Panics
+    /// `align` must be a power of two and at least `align_of::<T>()`. This isn't checked
+    /// here (there's no allocation yet to misalign), but is asserted in `reserve()`/
+    /// `try_reserve()`, which every other growth path (including `push()`) funnels through.
+    pub const fn new(align: usize) -> Self {
+        AVec {
+            ptr: NonNull::dangling(),
+            len: 0,
+            cap: 0,
+            align,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Create an `AVec` with space for at least `capacity` uninitialised elements of `T`,
+    /// aligned to `align` bytes.
+    ///
+    /// # Panics
+    /// Panics if `align` isn't a power of two at least `align_of::<T>()`, or if the resulting
+    /// layout overflows `isize::MAX`. If the allocator fails, `handle_alloc_error()` is
+    /// called, which aborts the process by default. See
+    /// [`try_with_capacity_aligned()`] for a fallible version.
+    pub fn with_capacity_aligned(capacity: usize, align: usize) -> Self {
+        match Self::try_with_capacity_aligned(capacity, align) {
+            Ok(v) => v,
+            Err(TryReserveError::AllocError(layout)) => handle_alloc_error(layout),
+            Err(TryReserveError::CapacityOverflow) => panic!("capacity overflow"),
+        }
+    }
+
+    /// Create an `AVec` with space for at least `capacity` uninitialised elements of `T`,
+    /// aligned to `align` bytes, reporting overflow or OOM as an `Err` instead of panicking
+    /// or aborting.
+    ///
+    /// The allocated capacity is left uninitialised; no element is readable until `push()`
+    /// writes to it, so there's no need to zero-fill the buffer up front.
+    ///
+    /// # Panics
+    /// Panics if `align` isn't a power of two, or is smaller than `align_of::<T>()`.
+    pub fn try_with_capacity_aligned(capacity: usize, align: usize) -> Result<Self, TryReserveError> {
+        assert!(align.is_power_of_two() && align >= mem::align_of::<T>());
+
+        if capacity == 0 {
+            return Ok(AVec { ptr: NonNull::dangling(), len: 0, cap: 0, align, _marker: PhantomData });
+        }
+
+        let layout = Self::layout_for(capacity, align).ok_or(TryReserveError::CapacityOverflow)?;
+
+        // SAFETY: `alloc` is only called when `layout` has a non-zero size; a zero-sized `T`
+        // gives `layout.size() == 0` and gets a dangling pointer aligned to `align` instead.
+        let raw = if layout.size() == 0 {
+            layout.align() as *mut u8
+        } else {
+            unsafe { alloc(layout) }
+        };
+
+        let ptr = NonNull::new(raw as *mut T).ok_or(TryReserveError::AllocError(layout))?;
+
+        Ok(AVec { ptr, len: 0, cap: capacity, align, _marker: PhantomData })
+    }
+
+    /// Reserve space for at least `additional` more elements beyond `self.len()`, keeping
+    /// the vector's current alignment.
+    ///
+    /// # Panics
+    /// Panics on capacity overflow; on allocator OOM, calls `handle_alloc_error()`, which
+    /// aborts the process by default. See [`try_reserve()`] for a fallible version.
+    pub fn reserve(&mut self, additional: usize) {
+        if let Err(err) = self.try_reserve(additional) {
+            match err {
+                TryReserveError::AllocError(layout) => handle_alloc_error(layout),
+                TryReserveError::CapacityOverflow => panic!("capacity overflow"),
+            }
+        }
+    }
+
+    /// Reserve space for at least `additional` more elements beyond `self.len()`, keeping
+    /// the vector's current alignment, reporting overflow or OOM as an `Err` instead of
+    /// panicking or aborting.
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        assert!(self.align.is_power_of_two() && self.align >= mem::align_of::<T>());
+
+        let required = self.len.checked_add(additional).ok_or(TryReserveError::CapacityOverflow)?;
+        if required <= self.cap {
+            return Ok(());
+        }
+
+        let new_cap = required.max(self.cap.saturating_mul(2)).max(1);
+        let new_layout = Self::layout_for(new_cap, self.align).ok_or(TryReserveError::CapacityOverflow)?;
+
+        let new_ptr = if self.cap == 0 || mem::size_of::<T>() == 0 {
+            if new_layout.size() == 0 {
+                new_layout.align() as *mut u8
+            } else {
+                unsafe { alloc(new_layout) }
+            }
+        } else {
+            // SAFETY: `self.ptr` was allocated with `Self::layout_for(self.cap, self.align)`.
+            let old_layout = Self::layout_for(self.cap, self.align).ok_or(TryReserveError::CapacityOverflow)?;
+            unsafe { std::alloc::realloc(self.ptr.as_ptr() as *mut u8, old_layout, new_layout.size()) }
+        };
+
+        let new_ptr = NonNull::new(new_ptr as *mut T).ok_or(TryReserveError::AllocError(new_layout))?;
+
+        self.ptr = new_ptr;
+        self.cap = new_cap;
+        Ok(())
+    }
+
+    /// Append `value` to the end of the vector, growing the backing allocation if needed.
+    ///
+    /// # Panics
+    /// Panics on capacity overflow; on allocator OOM, calls `handle_alloc_error()`, which
+    /// aborts the process by default. See [`try_reserve()`] to avoid this.
+    pub fn push(&mut self, value: T) {
+        if self.len == self.cap {
+            self.reserve(1);
+        }
+        // SAFETY: `self.len < self.cap` after the `reserve()` above.
+        unsafe {
+            ptr::write(self.ptr.as_ptr().add(self.len), value);
+        }
+        self.len += 1;
+    }
+
+    /// The number of initialised elements currently in the vector.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the vector currently holds no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The number of elements the current allocation can hold without reallocating.
+    pub fn capacity(&self) -> usize {
+        self.cap
+    }
+
+    /// Borrow the initialised elements of the vector as a slice.
+    pub fn as_slice(&self) -> &[T] {
+        // SAFETY: The first `self.len` elements are initialised by construction.
+        unsafe { core::slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+
+    /// Borrow the initialised elements of the vector as a mutable slice.
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        // SAFETY: The first `self.len` elements are initialised by construction.
+        unsafe { core::slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+
+    fn layout_for(capacity: usize, align: usize) -> Option<Layout> {
+        let size = mem::size_of::<T>().checked_mul(capacity)?;
+        Layout::from_size_align(size, align).ok()
+    }
+}
+
+impl<T> Drop for AVec<T> {
+    fn drop(&mut self) {
+        if mem::needs_drop::<T>() {
+            // SAFETY: The first `self.len` elements are initialised.
+            unsafe {
+                ptr::drop_in_place(self.as_mut_slice() as *mut _);
+            }
+        }
+        if self.cap > 0 {
+            if let Some(layout) = Self::layout_for(self.cap, self.align) {
+                if layout.size() > 0 {
+                    // SAFETY: `self.ptr` was allocated with this same layout.
+                    unsafe {
+                        dealloc(self.ptr.as_ptr() as *mut u8, layout);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<T> core::ops::Deref for AVec<T> {
+    type Target = [T];
+    fn deref(&self) -> &[T] {
+        self.as_slice()
+    }
+}
+
+impl<T> core::ops::DerefMut for AVec<T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        self.as_mut_slice()
+    }
+}
@@ -120,6 +120,9 @@ pub mod avec;
 pub use avec::AVec;
 
 mod ffi;
+mod stack_limit;
+
+pub use stack_limit::{remaining_stack, DEFAULT_STACK_MARGIN};
 
 /// Allocate a runtime length uninitialised byte buffer on the stack, call `callback` with this buffer, and then deallocate the buffer.
 ///
@@ -380,6 +383,59 @@ where F: FnOnce(&mut [T]) -> U,
 }
 
 
+/// Allocate a runtime length slice of `T` on the stack if `size * size_of::<T>()` is at or
+/// below `threshold_bytes`, fill it by calling `init_with`, call `callback` with this buffer,
+/// and then drop and deallocate the buffer. If the buffer would be larger than
+/// `threshold_bytes`, a heap allocated `Vec<T>` is used instead, entirely transparently to
+/// `callback`.
+///
+/// This addresses the `alloca()` footgun of passing an unvalidated runtime size straight
+/// through to a stack allocation: pass the same untrusted size here and oversized requests
+/// spill to the heap instead of overflowing the stack.
+///
+/// # Notes
+/// Not available under the `no_std` feature, since the heap fallback needs an allocator.
+#[cfg(not(feature = "no_std"))]
+pub fn stackalloc_or_heap_with<T, U, F, I>(size: usize, threshold_bytes: usize, mut init_with: I, callback: F) -> U
+where F: FnOnce(&mut [T]) -> U,
+      I: FnMut() -> T
+{
+    if matches!(size.checked_mul(core::mem::size_of::<T>()), Some(bytes) if bytes <= threshold_bytes) {
+	stackalloc_with(size, init_with, callback)
+    } else {
+	let mut heap: Vec<T> = (0..size).map(|_| init_with()).collect();
+	callback(&mut heap[..])
+    }
+}
+
+/// Allocate a runtime length slice of `T` on the stack if `size * size_of::<T>()` is at or
+/// below `threshold_bytes`, fill it by cloning `init`, call `callback` with this buffer, and
+/// then drop and deallocate the buffer. Falls back to a heap allocated `Vec<T>` otherwise.
+///
+/// See [`stackalloc_or_heap_with()`].
+#[cfg(not(feature = "no_std"))]
+#[inline] pub fn stackalloc_or_heap<T, U, F>(size: usize, threshold_bytes: usize, init: T, callback: F) -> U
+where F: FnOnce(&mut [T]) -> U,
+      T: Clone
+{
+    stackalloc_or_heap_with(size, threshold_bytes, move || init.clone(), callback)
+}
+
+/// Allocate a runtime length slice of `T` on the stack if `size * size_of::<T>()` is at or
+/// below `threshold_bytes`, fill it by calling `T::default()`, call `callback` with this
+/// buffer, and then drop and deallocate the buffer. Falls back to a heap allocated `Vec<T>`
+/// otherwise.
+///
+/// See [`stackalloc_or_heap_with()`].
+#[cfg(not(feature = "no_std"))]
+#[inline] pub fn stackalloc_or_heap_with_default<T, U, F>(size: usize, threshold_bytes: usize, callback: F) -> U
+where F: FnOnce(&mut [T]) -> U,
+      T: Default
+{
+    stackalloc_or_heap_with(size, threshold_bytes, T::default, callback)
+}
+
+
 /// Collect an iterator into a stack allocated buffer up to `size` elements, call `callback` with this buffer, and then drop and deallocate the buffer.
 ///
 /// See `stackalloc()`.
@@ -452,6 +508,211 @@ where F: FnOnce(&mut [T]) -> U,
     }, iter, callback)
 }
 
+/// The error returned by `try_stackalloc_from_iter()` and
+/// `try_stackalloc_from_iter_filled()` when the iterator yields more than `size` elements.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CollectError {
+    /// The iterator was still yielding elements after the `size`-element buffer was filled.
+    /// The remainder of the iterator has already been drained (and dropped) by the time this
+    /// error is returned.
+    Overflow {
+        /// The capacity of the buffer that was filled.
+        capacity: usize,
+    },
+}
+
+impl core::fmt::Display for CollectError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            CollectError::Overflow { capacity } => write!(
+                f,
+                "iterator yielded more than the {capacity}-element buffer could hold"
+            ),
+        }
+    }
+}
+
+#[cfg(not(feature = "no_std"))]
+impl std::error::Error for CollectError {}
+
+/// Collect an iterator into a stack allocated buffer of exactly `size` elements, call
+/// `callback` with `(&mut [T], filled)`, and then drop and deallocate the buffer.
+///
+/// Unlike `stackalloc_with_iter()`, which silently truncates, this reports the iterator
+/// shape explicitly: `filled` is `true` if the buffer was filled to capacity (the iterator
+/// may have had exactly `size` elements), or `false` if the iterator ran out before filling
+/// the buffer (the slice passed to `callback` is then shorter than `size`). If the iterator
+/// yields more than `size` elements, the remainder is drained and dropped, and
+/// `Err(CollectError::Overflow { capacity: size })` is returned without calling `callback`
+/// at all.
+pub fn try_stackalloc_from_iter_filled<I, T, U, F>(size: usize, iter: I, callback: F) -> Result<U, CollectError>
+where F: FnOnce(&mut [T], bool) -> U,
+      I: IntoIterator<Item = T>,
+{
+    stackalloc_uninit(size, move |buf| {
+	let mut iter = iter.into_iter();
+	let mut done = 0;
+	while done < size {
+	    match iter.next() {
+		Some(item) => {
+		    buf[done] = MaybeUninit::new(item);
+		    done += 1;
+		}
+		None => break,
+	    }
+	}
+
+	let overflowed = done == size && iter.next().is_some();
+
+	// SAFETY: We just initialised `done` elements of `buf` above.
+	let init = unsafe { slice_assume_init_mut(&mut buf[..done]) };
+
+	if overflowed {
+	    if mem::needs_drop::<T>() {
+		// SAFETY: `init` holds `done` initialised elements, and is never touched again.
+		unsafe {
+		    ptr::drop_in_place(init as *mut _);
+		}
+	    }
+	    // Drain (and drop) whatever is left of the iterator, since we're reporting an
+	    // error instead of handing it to the caller.
+	    for item in iter {
+		drop(item);
+	    }
+	    return Err(CollectError::Overflow { capacity: size });
+	}
+
+	let filled = done == size;
+	let ret = callback(init, filled);
+	if mem::needs_drop::<T>() {
+	    // SAFETY: `init` holds `done` initialised elements.
+	    unsafe {
+		ptr::drop_in_place(init as *mut _);
+	    }
+	}
+	Ok(ret)
+    })
+}
+
+/// Collect an iterator into a stack allocated buffer of exactly `size` elements, call
+/// `callback` with the buffer, and then drop and deallocate the buffer.
+///
+/// This is [`try_stackalloc_from_iter_filled()`] without the `filled` flag, for callers
+/// that only care whether the iterator overflowed the buffer.
+#[inline] pub fn try_stackalloc_from_iter<I, T, U, F>(size: usize, iter: I, callback: F) -> Result<U, CollectError>
+where F: FnOnce(&mut [T]) -> U,
+      I: IntoIterator<Item = T>,
+{
+    try_stackalloc_from_iter_filled(size, iter, move |buf, _filled| callback(buf))
+}
+
+
+/// The error returned by the `try_*` family when a request is rejected up front because it
+/// would likely overflow the stack, instead of being attempted and letting the OS abort the
+/// process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StackError {
+    /// The requested allocation (plus alignment padding and [`DEFAULT_STACK_MARGIN`]) is
+    /// larger than the stack space [`remaining_stack()`] reported as available.
+    Exhausted {
+        /// The number of bytes that were requested (including alignment padding, but not
+        /// [`DEFAULT_STACK_MARGIN`]).
+        requested: usize,
+        /// The number of bytes [`remaining_stack()`] reported as available.
+        available: usize,
+    },
+}
+
+impl core::fmt::Display for StackError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            StackError::Exhausted { requested, available } => write!(
+                f,
+                "requested {requested} bytes of stack but only {available} bytes remain"
+            ),
+        }
+    }
+}
+
+#[cfg(not(feature = "no_std"))]
+impl std::error::Error for StackError {}
+
+/// Check `size_bytes` (plus `align` padding and [`DEFAULT_STACK_MARGIN`]) against
+/// [`remaining_stack()`], returning `Err` if the request looks unsafe.
+///
+/// `size_bytes` is `None` if the caller's own `size * size_of::<T>()` already overflowed
+/// `usize`; that's treated the same as any other oversized request, never let through.
+///
+/// If the platform can't be probed (`remaining_stack()` returns `None`), and the request
+/// itself didn't overflow, this conservatively lets the request through, falling back to the
+/// infallible behaviour documented on `alloca()`.
+fn check_stack_space(size_bytes: Option<usize>, align: usize) -> Result<(), StackError> {
+    let available = remaining_stack();
+
+    let requested = size_bytes
+        .and_then(|size_bytes| size_bytes.checked_add(align))
+        .ok_or_else(|| StackError::Exhausted { requested: usize::MAX, available: available.unwrap_or(0) })?;
+
+    match available {
+        Some(available) => match requested.checked_add(DEFAULT_STACK_MARGIN) {
+            Some(total) if total <= available => Ok(()),
+            _ => Err(StackError::Exhausted { requested, available }),
+        },
+        None => Ok(()),
+    }
+}
+
+/// Fallible counterpart to [`alloca()`].
+///
+/// Before extending the stack, the requested `size` is checked against [`remaining_stack()`].
+/// If the request looks like it would overflow the stack, `Err(StackError::Exhausted { .. })`
+/// is returned instead of committing to the allocation. See `alloca()` for everything else.
+///
+/// On platforms where `remaining_stack()` returns `None` (every target other than Linux with
+/// glibc, or a thread that can't be probed), the request is let through unconditionally.
+pub fn try_alloca<T, F>(size: usize, callback: F) -> Result<T, StackError>
+where F: FnOnce(&mut [MaybeUninit<u8>]) -> T
+{
+    check_stack_space(Some(size), 1)?;
+    Ok(alloca(size, callback))
+}
+
+/// Fallible counterpart to [`alloca_zeroed()`]. See [`try_alloca()`].
+#[inline] pub fn try_alloca_zeroed<T, F>(size: usize, callback: F) -> Result<T, StackError>
+where F: FnOnce(&mut [u8]) -> T
+{
+    check_stack_space(Some(size), 1)?;
+    Ok(alloca_zeroed(size, callback))
+}
+
+/// Fallible counterpart to [`stackalloc_uninit()`]. See [`try_alloca()`].
+#[inline] pub fn try_stackalloc_uninit<T, U, F>(size: usize, callback: F) -> Result<U, StackError>
+where F: FnOnce(&mut [MaybeUninit<T>]) -> U
+{
+    check_stack_space(size.checked_mul(core::mem::size_of::<T>()), core::mem::align_of::<T>())?;
+    Ok(stackalloc_uninit(size, callback))
+}
+
+/// Fallible counterpart to [`stackalloc_with()`]. See [`try_alloca()`].
+#[inline] pub fn try_stackalloc_with<T, U, F, I>(size: usize, init_with: I, callback: F) -> Result<U, StackError>
+where F: FnOnce(&mut [T]) -> U,
+      I: FnMut() -> T
+{
+    check_stack_space(size.checked_mul(core::mem::size_of::<T>()), core::mem::align_of::<T>())?;
+    Ok(stackalloc_with(size, init_with, callback))
+}
+
+/// Fallible counterpart to [`stackalloc_with_iter()`]. See [`try_alloca()`].
+///
+/// Note that this still only checks `size` (the capacity of the buffer) against the
+/// remaining stack, not the number of elements the iterator will actually yield.
+#[inline] pub fn try_stackalloc_with_iter<I, T, U, F>(size: usize, iter: I, callback: F) -> Result<U, StackError>
+where F: FnOnce(&mut [T]) -> U,
+      I: IntoIterator<Item = T>,
+{
+    check_stack_space(size.checked_mul(core::mem::size_of::<T>()), core::mem::align_of::<T>())?;
+    Ok(stackalloc_with_iter(size, iter, callback))
+}
 
 #[cfg(test)]
 mod tests;
@@ -0,0 +1,139 @@
+//! Best-effort introspection of the remaining space on the current thread's stack.
+//!
+//! This is used by the `try_*` family in the crate root to reject requests that would
+//! almost certainly overflow the stack, instead of committing to the `alloca()` and
+//! letting the OS terminate the process.
+
+/// The amount of headroom (in bytes) subtracted from the measured remaining stack space
+/// before a `try_*` function will accept a request.
+///
+/// This leaves room for the `alloca` trampoline's own frame, the callback's frame, and
+/// any stack probes the platform inserts, none of which are accounted for by the raw
+/// "distance to the end of the stack" measurement.
+pub const DEFAULT_STACK_MARGIN: usize = 16 * 1024;
+
+/// Returns the number of bytes remaining between the current stack pointer (approximated
+/// by the address of a local variable) and the low address end of the thread's stack.
+///
+/// Returns `None` if the current platform (or thread) can't be probed, in which case
+/// callers should fall back to the infallible behaviour.
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+pub fn remaining_stack() -> Option<usize> {
+    // SAFETY: `local` is only used for its address, to approximate the current stack pointer.
+    let local = 0u8;
+    let approx_sp = &local as *const u8 as usize;
+
+    let (stack_lowest, stack_size) = unsafe { pthread_stack_range()? };
+
+    let stack_highest = stack_lowest.checked_add(stack_size)?;
+    if approx_sp < stack_lowest || approx_sp > stack_highest {
+        // Something about our assumptions didn't hold; don't guess.
+        return None;
+    }
+
+    Some(approx_sp - stack_lowest)
+}
+
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+unsafe fn pthread_stack_range() -> Option<(usize, usize)> {
+    use core::mem::MaybeUninit;
+
+    // The main thread's `tid` is equal to the process's `pid`; every other thread's isn't.
+    let is_main_thread = ffi::getpid() == ffi::gettid();
+
+    if is_main_thread {
+        // The main thread's `pthread_attr_getstack` reports the `ulimit` at process start,
+        // not the live `RLIMIT_STACK`, so query the resource limit directly instead.
+        let mut limit = MaybeUninit::<ffi::rlimit>::uninit();
+        if ffi::getrlimit(ffi::RLIMIT_STACK, limit.as_mut_ptr()) != 0 {
+            return None;
+        }
+        let limit = limit.assume_init();
+        if limit.rlim_cur == ffi::RLIM_INFINITY {
+            return None;
+        }
+
+        // The main thread's stack grows down from a fixed high address set by glibc's
+        // startup code before `main` runs. Unlike the current stack pointer, this doesn't
+        // move as the call stack grows deeper, so combine it with the resource limit to
+        // get a low end that reflects genuine remaining headroom rather than the fixed
+        // limit itself.
+        let stack_top = ffi::libc_stack_end() as usize;
+        let stack_size = limit.rlim_cur as usize;
+        return Some((stack_top.checked_sub(stack_size)?, stack_size));
+    }
+
+    let mut attr = MaybeUninit::<ffi::pthread_attr_t>::uninit();
+    if ffi::pthread_getattr_np(ffi::pthread_self(), attr.as_mut_ptr()) != 0 {
+        return None;
+    }
+    let mut attr = attr.assume_init();
+
+    let mut base = core::ptr::null_mut();
+    let mut size = 0usize;
+    let got = ffi::pthread_attr_getstack(&attr, &mut base, &mut size);
+    ffi::pthread_attr_destroy(&mut attr);
+
+    if got != 0 {
+        return None;
+    }
+
+    Some((base as usize, size))
+}
+
+#[cfg(not(all(target_os = "linux", target_env = "gnu")))]
+pub fn remaining_stack() -> Option<usize> {
+    None
+}
+
+/// Hand-written declarations for the small slice of glibc's pthread/rlimit API that
+/// `remaining_stack()` needs. These are intentionally narrow rather than pulled in via a
+/// `libc`-style dependency, since nothing else in the crate needs one.
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+#[allow(non_camel_case_types)]
+mod ffi {
+    use core::ffi::c_int;
+
+    pub type pthread_t = usize;
+    pub const RLIMIT_STACK: c_int = 3;
+    pub const RLIM_INFINITY: u64 = !0;
+
+    // Opaque storage at least as large as glibc's `pthread_attr_t` on Linux (56 bytes on
+    // x86_64, 36 on 32-bit x86 and ARM, 64 on aarch64).
+    #[repr(C, align(8))]
+    #[derive(Clone, Copy)]
+    pub struct pthread_attr_t([u8; 64]);
+
+    #[repr(C)]
+    pub struct rlimit {
+        pub rlim_cur: u64,
+        pub rlim_max: u64,
+    }
+
+    extern "C" {
+        pub fn pthread_self() -> pthread_t;
+        pub fn getpid() -> c_int;
+        pub fn gettid() -> c_int;
+        pub fn pthread_getattr_np(thread: pthread_t, attr: *mut pthread_attr_t) -> c_int;
+        pub fn pthread_attr_destroy(attr: *mut pthread_attr_t) -> c_int;
+        pub fn pthread_attr_getstack(
+            attr: *const pthread_attr_t,
+            stackaddr: *mut *mut core::ffi::c_void,
+            stacksize: *mut usize,
+        ) -> c_int;
+        pub fn getrlimit(resource: c_int, rlim: *mut rlimit) -> c_int;
+
+        /// Set by glibc's startup code to (approximately) the initial stack pointer of the
+        /// main thread, before `main` runs. Fixed for the lifetime of the process, unlike
+        /// the current stack pointer.
+        static __libc_stack_end: *mut core::ffi::c_void;
+    }
+
+    /// Wrapper so callers don't need an `unsafe` block just to read a `static`.
+    #[inline]
+    pub fn libc_stack_end() -> *mut core::ffi::c_void {
+        // SAFETY: `__libc_stack_end` is initialised by the CRT before any Rust code runs
+        // and is read-only for the remainder of the process's lifetime.
+        unsafe { __libc_stack_end }
+    }
+}
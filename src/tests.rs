@@ -0,0 +1,135 @@
+use super::*;
+use core::cell::Cell;
+
+/// A value that records into a shared counter when dropped, for asserting drop counts.
+struct DropCounter<'a>(&'a Cell<u32>);
+
+impl<'a> Drop for DropCounter<'a> {
+    fn drop(&mut self) {
+        self.0.set(self.0.get() + 1);
+    }
+}
+
+#[test]
+fn stackalloc_or_heap_stays_on_stack_at_and_below_threshold() {
+    // 4 * size_of::<u32>() == 16, exactly the threshold: should take the stack path.
+    let sum: u32 = stackalloc_or_heap(4, 16, 0u32, |buf| {
+        buf.iter_mut().enumerate().for_each(|(i, v)| *v = i as u32);
+        buf.iter().sum()
+    });
+    assert_eq!(sum, 0 + 1 + 2 + 3);
+}
+
+#[test]
+fn stackalloc_or_heap_spills_to_heap_above_threshold() {
+    // 4 * size_of::<u32>() == 16, one byte over the threshold: should spill to the heap.
+    let sum: u32 = stackalloc_or_heap(4, 15, 0u32, |buf| {
+        buf.iter_mut().enumerate().for_each(|(i, v)| *v = i as u32);
+        buf.iter().sum()
+    });
+    assert_eq!(sum, 0 + 1 + 2 + 3);
+}
+
+#[test]
+fn stackalloc_or_heap_drops_elements_on_both_paths() {
+    let dropped = Cell::new(0u32);
+    // Threshold is exactly the buffer size, so this takes the stack path.
+    let threshold = 4 * core::mem::size_of::<DropCounter>();
+    stackalloc_or_heap_with(4, threshold, || DropCounter(&dropped), |buf| assert_eq!(buf.len(), 4));
+    assert_eq!(dropped.get(), 4);
+
+    let dropped = Cell::new(0u32);
+    stackalloc_or_heap_with(4, 0, || DropCounter(&dropped), |buf| assert_eq!(buf.len(), 4));
+    assert_eq!(dropped.get(), 4);
+}
+
+#[test]
+fn try_stackalloc_from_iter_reports_overflow_and_drops_everything() {
+    let dropped = Cell::new(0u32);
+    let called = Cell::new(false);
+
+    let result = try_stackalloc_from_iter(3, (0..5).map(|_| DropCounter(&dropped)), |_buf| {
+        called.set(true);
+    });
+
+    assert!(matches!(result, Err(CollectError::Overflow { capacity: 3 })));
+    assert!(!called.get(), "callback must not run when the iterator overflows the buffer");
+    assert_eq!(dropped.get(), 5, "both the buffered and the drained elements must be dropped");
+}
+
+#[test]
+fn try_stackalloc_from_iter_filled_reports_filled_exactly() {
+    let result = try_stackalloc_from_iter_filled(3, 0..3, |buf, filled| {
+        assert_eq!(buf, &[0, 1, 2]);
+        assert!(filled);
+    });
+    assert!(result.is_ok());
+}
+
+#[test]
+fn try_stackalloc_from_iter_filled_reports_not_filled_when_shorter() {
+    let result = try_stackalloc_from_iter_filled(5, 0..3, |buf, filled| {
+        assert_eq!(buf, &[0, 1, 2]);
+        assert!(!filled);
+    });
+    assert!(result.is_ok());
+}
+
+#[test]
+fn avec_push_and_grow() {
+    let mut v: AVec<u32> = AVec::new(4);
+    for i in 0..100 {
+        v.push(i);
+    }
+    assert_eq!(v.len(), 100);
+    assert!(v.capacity() >= 100);
+    assert_eq!(&v.as_slice()[..5], &[0, 1, 2, 3, 4]);
+}
+
+#[test]
+fn avec_drops_elements() {
+    let dropped = Cell::new(0u32);
+    {
+        let mut v: AVec<DropCounter> = AVec::new(8);
+        v.push(DropCounter(&dropped));
+        v.push(DropCounter(&dropped));
+        v.push(DropCounter(&dropped));
+        assert_eq!(dropped.get(), 0);
+    }
+    assert_eq!(dropped.get(), 3);
+}
+
+#[test]
+fn avec_zero_sized_type() {
+    let mut v: AVec<()> = AVec::new(1);
+    for _ in 0..10 {
+        v.push(());
+    }
+    assert_eq!(v.len(), 10);
+}
+
+#[test]
+fn avec_try_with_capacity_aligned_respects_alignment() {
+    let v: AVec<u8> = AVec::try_with_capacity_aligned(64, 64).unwrap();
+    let addr = v.as_slice().as_ptr() as usize;
+    assert_eq!(addr % 64, 0);
+}
+
+#[test]
+fn avec_try_reserve_does_not_shrink_below_len() {
+    let mut v: AVec<u32> = AVec::try_with_capacity_aligned(2, 4).unwrap();
+    v.push(1);
+    v.push(2);
+    assert!(v.try_reserve(10).is_ok());
+    assert!(v.capacity() >= 12);
+    assert_eq!(v.as_slice(), &[1, 2]);
+}
+
+#[test]
+#[should_panic]
+fn avec_new_rejects_alignment_weaker_than_element() {
+    // `align_of::<u64>()` is 8; an `align` of 1 must be rejected on first growth rather
+    // than silently producing a misaligned allocation.
+    let mut v: AVec<u64> = AVec::new(1);
+    v.push(7);
+}